@@ -1,7 +1,9 @@
-use clap::{builder::ArgPredicate, ArgAction, Args, Parser};
-use std::collections::HashMap;
+use clap::{builder::ArgPredicate, ArgAction, Args, CommandFactory, FromArgMatches, Parser, ValueEnum};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
 use std::env;
 use std::num::ParseIntError;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 use url;
 
@@ -13,6 +15,13 @@ pub struct ChDigOptions {
     pub clickhouse: ClickHouseOptions,
     #[command(flatten)]
     pub view: ViewOptions,
+
+    #[arg(long, value_name = "PATH")]
+    /// Path to a chdig.toml config file (default: $XDG_CONFIG_HOME/chdig/, ~/.config/chdig/, ./)
+    pub config: Option<String>,
+    #[arg(long, value_name = "NAME")]
+    /// Named connection profile to use from the config file
+    pub connection: Option<String>,
 }
 
 #[derive(Args, Clone)]
@@ -30,6 +39,69 @@ pub struct ClickHouseOptions {
     pub url_safe: String,
     #[arg(short('c'), long)]
     pub cluster: Option<String>,
+
+    #[arg(long, action = ArgAction::SetTrue)]
+    /// Use a secure (TLS) connection to the ClickHouse server
+    pub secure: bool,
+    #[arg(long, action = ArgAction::SetTrue)]
+    /// Do not verify the server TLS certificate (useful for self-signed certs)
+    pub skip_verify: bool,
+
+    #[arg(long, default_value_t = 10, value_name = "N")]
+    /// Minimum number of connections to keep in the pool
+    pub pool_min: u16,
+    #[arg(long, default_value_t = 20, value_name = "N")]
+    /// Maximum number of connections in the pool
+    pub pool_max: u16,
+    #[arg(long, default_value = "5s", value_name = "DURATION")]
+    /// How long to wait for a free connection from the pool before giving up
+    pub pool_wait_timeout: String,
+
+    #[arg(long, value_enum, default_value_t = Compression::Lz4)]
+    /// Wire protocol compression for query results
+    pub compression: Compression,
+
+    #[arg(long = "host", value_name = "HOST[:PORT]")]
+    /// Additional ClickHouse host to fail over to if the primary is unreachable (repeatable)
+    pub extra_hosts: Vec<String>,
+    // Ordered list of "host[:port]" endpoints to try on connect, starting with
+    // the primary host (from --url/-u), then any comma-separated hosts in its
+    // authority, then any --host flags.
+    #[clap(skip)]
+    pub hosts: Vec<String>,
+
+    #[arg(long = "setting", value_name = "KEY=VALUE", value_parser = parse_key_value)]
+    /// ClickHouse session setting applied to every query, e.g. --setting max_threads=4 (repeatable)
+    pub settings: Vec<(String, String)>,
+    #[arg(long = "param", value_name = "NAME=VALUE", value_parser = parse_key_value)]
+    /// Query parameter substituted into every introspection query, e.g. --param threshold=100 (repeatable)
+    pub params: Vec<(String, String)>,
+}
+
+fn parse_key_value(arg: &str) -> Result<(String, String), String> {
+    return arg
+        .split_once('=')
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .ok_or_else(|| format!("expected KEY=VALUE, got \"{}\"", arg));
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    None,
+    Lz4,
+    Zstd,
+}
+
+impl std::fmt::Display for Compression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Compression::None => "none",
+            Compression::Lz4 => "lz4",
+            Compression::Zstd => "zstd",
+        };
+        write!(f, "{}", s)
+    }
 }
 
 #[derive(Args, Clone)]
@@ -59,18 +131,108 @@ pub struct ViewOptions {
     no_mouse: bool,
 }
 
-fn parse_url(url_str: &str) -> url::Url {
+// clickhouse-rs style connection strings allow a comma-separated host list in
+// the authority (e.g. "tcp://user@host1:9000,host2:9000/"), but `url::Url`
+// only understands a single host. Split the extra hosts off before parsing,
+// keeping the first host to build a regular `url::Url` from.
+fn split_hosts(url_str: &str) -> (String, Vec<String>) {
+    let (prefix, rest) = match url_str.find("://") {
+        Some(pos) => (&url_str[..pos + 3], &url_str[pos + 3..]),
+        None => ("", url_str),
+    };
+    // userinfo (if any) is shared across all hosts, keep it only on the primary
+    let (userinfo, host_and_rest) = match rest.rfind('@') {
+        Some(pos) => (&rest[..pos + 1], &rest[pos + 1..]),
+        None => ("", rest),
+    };
+    let end = host_and_rest
+        .find(['/', '?'])
+        .unwrap_or(host_and_rest.len());
+    let (hosts_part, tail) = host_and_rest.split_at(end);
+
+    let mut hosts = hosts_part.split(',').map(|host| host.trim());
+    let primary = hosts.next().unwrap_or_default();
+    let extra_hosts: Vec<String> = hosts
+        .filter(|host| !host.is_empty())
+        .map(|host| host.to_string())
+        .collect();
+
+    return (
+        format!("{}{}{}{}", prefix, userinfo, primary, tail),
+        extra_hosts,
+    );
+}
+
+fn parse_url(url_str: &str, secure: bool) -> (url::Url, Vec<String>) {
     // url::Url::scheme() does not works as we want,
     // since for "foo:bar@127.1" the scheme will be "foo",
-    if url_str.contains("://") {
-        return url::Url::parse(url_str).unwrap();
-    }
+    let normalized = if url_str.contains("://") {
+        url_str.to_string()
+    } else {
+        let scheme = if secure { "tcps" } else { "tcp" };
+        format!("{}://{}", scheme, url_str)
+    };
 
-    return url::Url::parse(&format!("tcp://{}", url_str)).unwrap();
+    let (primary, extra_hosts) = split_hosts(&normalized);
+    return (url::Url::parse(&primary).unwrap(), extra_hosts);
+}
+
+fn host_with_port(url: &url::Url) -> String {
+    return match url.port() {
+        Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+        None => url.host_str().unwrap_or_default().to_string(),
+    };
+}
+
+// mirrors the "default port for secure native connections" rule applied to
+// the primary host, for the other entries in `hosts` (which are plain
+// "host[:port]" strings, not `url::Url`s)
+fn host_with_default_port(host: &str, secure: bool) -> String {
+    if secure && !host.contains(':') {
+        return format!("{}:9440", host);
+    }
+    return host.to_string();
 }
 
 fn clickhouse_url_defaults(options: &mut ChDigOptions) {
-    let mut url = parse_url(&options.clickhouse.url);
+    let (mut url, extra_hosts) = parse_url(&options.clickhouse.url, options.clickhouse.secure);
+
+    // "secure"/"skip_verify" can come from --secure/--skip-verify, from the
+    // tcps/https scheme, or already be present in the URL query string.
+    let existing_pairs: HashMap<_, _> = url.query_pairs().into_owned().collect();
+    let is_secure = matches!(url.scheme(), "tcps" | "https")
+        || options.clickhouse.secure
+        || existing_pairs
+            .get("secure")
+            .map(|v| v == "true")
+            .unwrap_or(false);
+    let skip_verify = options.clickhouse.skip_verify
+        || existing_pairs
+            .get("skip_verify")
+            .map(|v| v == "true")
+            .unwrap_or(false);
+
+    // default port for secure native connections when only a host was given
+    if is_secure && url.port().is_none() {
+        url.set_port(Some(9440)).unwrap();
+    }
+
+    // ordered failover endpoints: primary host, then hosts embedded in the
+    // URL authority, then any --host flags
+    let mut hosts = vec![host_with_port(&url)];
+    hosts.extend(
+        extra_hosts
+            .iter()
+            .map(|host| host_with_default_port(host, is_secure)),
+    );
+    hosts.extend(
+        options
+            .clickhouse
+            .extra_hosts
+            .iter()
+            .map(|host| host_with_default_port(host, is_secure)),
+    );
+    options.clickhouse.hosts = hosts;
 
     if url.username().is_empty() {
         if let Ok(env_user) = env::var("CLICKHOUSE_USER") {
@@ -94,15 +256,55 @@ fn clickhouse_url_defaults(options: &mut ChDigOptions) {
     // some default settings in URL
     {
         let pairs: HashMap<_, _> = url_safe.query_pairs().into_owned().collect();
+        // tracks every key already present in the URL or appended below, so a
+        // later default never appends a key a user-provided --setting/--param
+        // (or the original URL) already covers
+        let mut reserved: HashSet<String> = pairs.keys().cloned().collect();
         let mut mut_pairs = url.query_pairs_mut();
+
+        // --setting/--param take precedence over chdig's own defaults below,
+        // so they claim their keys first and the defaults back off instead
+        // of being appended twice.
+        for (key, value) in &options.clickhouse.settings {
+            if reserved.insert(key.clone()) {
+                mut_pairs.append_pair(key, value);
+            }
+        }
+        for (name, value) in &options.clickhouse.params {
+            let key = format!("param_{}", name);
+            if reserved.insert(key.clone()) {
+                mut_pairs.append_pair(&key, value);
+            }
+        }
+
         // default is: 500ms (too small)
-        if !pairs.contains_key("connection_timeout") {
+        if reserved.insert("connection_timeout".to_string()) {
             mut_pairs.append_pair("connection_timeout", "5s");
         }
         // FIXME: Slow queries processing can be slow, and default timeout 180s may not be enough.
-        if !pairs.contains_key("query_timeout") {
+        if reserved.insert("query_timeout".to_string()) {
             mut_pairs.append_pair("query_timeout", "600s");
         }
+        if is_secure && reserved.insert("secure".to_string()) {
+            mut_pairs.append_pair("secure", "true");
+        }
+        if skip_verify && reserved.insert("skip_verify".to_string()) {
+            mut_pairs.append_pair("skip_verify", "true");
+        }
+        // connection pool tuning (useful when fanning out per-node queries with --cluster)
+        if reserved.insert("pool_min".to_string()) {
+            mut_pairs.append_pair("pool_min", &options.clickhouse.pool_min.to_string());
+        }
+        if reserved.insert("pool_max".to_string()) {
+            mut_pairs.append_pair("pool_max", &options.clickhouse.pool_max.to_string());
+        }
+        if reserved.insert("pool_wait_timeout".to_string()) {
+            mut_pairs.append_pair("pool_wait_timeout", &options.clickhouse.pool_wait_timeout);
+        }
+        // reduces bandwidth for the periodic polling loop driven by delay_interval
+        if reserved.insert("compression".to_string()) {
+            mut_pairs.append_pair("compression", &options.clickhouse.compression.to_string());
+        }
     }
     options.clickhouse.url = url.to_string();
 }
@@ -121,19 +323,393 @@ fn adjust_defaults(options: &mut ChDigOptions) {
     }
 }
 
-// TODO:
-// - config, I tried twelf but it is too buggy for now [1], let track [2] instead, I've also tried
-//   viperus for the first version of this program, but it was even more buggy and does not support
-//   new clap, and also it is not maintained anymore.
-//
-//     [1]: https://github.com/clap-rs/clap/discussions/2763
-//     [2]: https://github.com/bnjjj/twelf/issues/15
-//
+// Layered config file, merged with precedence CLI > env > file > built-in defaults.
+// (twelf and viperus were tried first and were too buggy for this, see chunk0-4)
+#[derive(Deserialize, Default, Clone)]
+#[serde(default)]
+struct ConnectionProfile {
+    url: Option<String>,
+    cluster: Option<String>,
+    view: Option<ViewConfigFile>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct ClickHouseConfigFile {
+    url: Option<String>,
+    cluster: Option<String>,
+    secure: Option<bool>,
+    skip_verify: Option<bool>,
+    pool_min: Option<u16>,
+    pool_max: Option<u16>,
+    pool_wait_timeout: Option<String>,
+    compression: Option<Compression>,
+}
+
+#[derive(Deserialize, Default, Clone)]
+#[serde(default)]
+struct ViewConfigFile {
+    delay_interval_ms: Option<u64>,
+    group_by: Option<bool>,
+    no_subqueries: Option<bool>,
+    mouse: Option<bool>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct ConfigFile {
+    // name of the profile to use when `--connection` is not passed
+    connection: Option<String>,
+    connections: HashMap<String, ConnectionProfile>,
+    clickhouse: Option<ClickHouseConfigFile>,
+    view: Option<ViewConfigFile>,
+}
+
+fn find_default_config_path() -> Option<PathBuf> {
+    let candidates = [
+        env::var("XDG_CONFIG_HOME")
+            .ok()
+            .map(|dir| PathBuf::from(dir).join("chdig").join("chdig.toml")),
+        env::var("HOME")
+            .ok()
+            .map(|dir| PathBuf::from(dir).join(".config/chdig/chdig.toml")),
+        Some(PathBuf::from("chdig.toml")),
+    ];
+    return candidates.into_iter().flatten().find(|path| path.is_file());
+}
+
+fn load_config_file(path: &Path) -> Result<ConfigFile, String> {
+    let contents =
+        std::fs::read_to_string(path).map_err(|err| format!("cannot read {}: {}", path.display(), err))?;
+    return toml::from_str(&contents).map_err(|err| format!("cannot parse {}: {}", path.display(), err));
+}
+
+// A `DefaultValue` source, or no source at all (an option without a default
+// that was not given), means the user did not pass the flag on the command
+// line nor via its `env` fallback, so a config file value is allowed to fill
+// it in.
+fn is_explicit(matches: &clap::ArgMatches, id: &str) -> bool {
+    return !matches!(
+        matches.value_source(id),
+        None | Some(clap::parser::ValueSource::DefaultValue)
+    );
+}
+
+fn apply_config_file(
+    options: &mut ChDigOptions,
+    matches: &clap::ArgMatches,
+    config: &ConfigFile,
+) -> Result<(), String> {
+    let profile_name = options.connection.as_deref().or(config.connection.as_deref());
+    let profile = match profile_name {
+        Some(name) => Some(
+            config
+                .connections
+                .get(name)
+                .ok_or_else(|| format!("connection profile \"{}\" not found in config file", name))?,
+        ),
+        None => None,
+    };
+
+    if !is_explicit(matches, "url") {
+        if let Some(url) = profile.and_then(|p| p.url.as_ref()) {
+            options.clickhouse.url = url.clone();
+        } else if let Some(url) = config.clickhouse.as_ref().and_then(|c| c.url.as_ref()) {
+            options.clickhouse.url = url.clone();
+        }
+    }
+    if !is_explicit(matches, "cluster") {
+        let cluster_from_file = profile
+            .and_then(|p| p.cluster.as_ref())
+            .or_else(|| config.clickhouse.as_ref().and_then(|c| c.cluster.as_ref()));
+        if let Some(cluster) = cluster_from_file {
+            options.clickhouse.cluster = Some(cluster.clone());
+            // `--cluster`'s `default_value_if("cluster", ...)` only sees argv
+            // at parse time, so a cluster selected through the config file
+            // instead of `--cluster` never gets that same group_by default;
+            // replicate it here when group_by/no_group_by were never explicit.
+            if !is_explicit(matches, "group_by") && !is_explicit(matches, "no_group_by") {
+                options.view.group_by = true;
+            }
+        }
+    }
+    if !is_explicit(matches, "secure") {
+        if let Some(v) = config.clickhouse.as_ref().and_then(|c| c.secure) {
+            options.clickhouse.secure = v;
+        }
+    }
+    if !is_explicit(matches, "skip_verify") {
+        if let Some(v) = config.clickhouse.as_ref().and_then(|c| c.skip_verify) {
+            options.clickhouse.skip_verify = v;
+        }
+    }
+    if !is_explicit(matches, "pool_min") {
+        if let Some(v) = config.clickhouse.as_ref().and_then(|c| c.pool_min) {
+            options.clickhouse.pool_min = v;
+        }
+    }
+    if !is_explicit(matches, "pool_max") {
+        if let Some(v) = config.clickhouse.as_ref().and_then(|c| c.pool_max) {
+            options.clickhouse.pool_max = v;
+        }
+    }
+    if !is_explicit(matches, "pool_wait_timeout") {
+        if let Some(v) = config
+            .clickhouse
+            .as_ref()
+            .and_then(|c| c.pool_wait_timeout.as_ref())
+        {
+            options.clickhouse.pool_wait_timeout = v.clone();
+        }
+    }
+    if !is_explicit(matches, "compression") {
+        if let Some(v) = config.clickhouse.as_ref().and_then(|c| c.compression) {
+            options.clickhouse.compression = v;
+        }
+    }
+
+    // merge per field, like every other section above, instead of picking
+    // the profile's or the top-level view table wholesale: a profile that
+    // only overrides e.g. group_by should not discard top-level delay_interval_ms/etc.
+    let profile_view = profile.and_then(|p| p.view.as_ref());
+    let top_view = config.view.as_ref();
+
+    if !is_explicit(matches, "delay_interval") {
+        let ms = profile_view
+            .and_then(|v| v.delay_interval_ms)
+            .or_else(|| top_view.and_then(|v| v.delay_interval_ms));
+        if let Some(ms) = ms {
+            options.view.delay_interval = Duration::from_millis(ms);
+        }
+    }
+    if !is_explicit(matches, "group_by") {
+        let v = profile_view
+            .and_then(|v| v.group_by)
+            .or_else(|| top_view.and_then(|v| v.group_by));
+        if let Some(v) = v {
+            options.view.group_by = v;
+        }
+    }
+    if !is_explicit(matches, "no_subqueries") {
+        let v = profile_view
+            .and_then(|v| v.no_subqueries)
+            .or_else(|| top_view.and_then(|v| v.no_subqueries));
+        if let Some(v) = v {
+            options.view.no_subqueries = v;
+        }
+    }
+    if !is_explicit(matches, "mouse") {
+        let v = profile_view
+            .and_then(|v| v.mouse)
+            .or_else(|| top_view.and_then(|v| v.mouse));
+        if let Some(v) = v {
+            options.view.mouse = v;
+        }
+    }
+
+    return Ok(());
+}
+
 // - clap_complete
 pub fn parse() -> ChDigOptions {
-    let mut options = ChDigOptions::parse();
+    let matches = ChDigOptions::command().get_matches();
+    let mut options = ChDigOptions::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+
+    let explicit_config_path = options.config.as_ref().map(PathBuf::from);
+    let config_path = explicit_config_path.clone().or_else(find_default_config_path);
+
+    let config = match config_path {
+        Some(path) => match load_config_file(&path) {
+            Ok(config) => config,
+            // a config file found via the default search path is optional, but one
+            // passed explicitly via --config must exist and be valid
+            Err(err) if explicit_config_path.is_some() => {
+                eprintln!("error: {}", err);
+                std::process::exit(1);
+            }
+            Err(_) => ConfigFile::default(),
+        },
+        None => ConfigFile::default(),
+    };
+
+    // run unconditionally, even with no config file at all, so an explicitly
+    // requested --connection profile is always validated rather than silently
+    // falling back to the default connection when no chdig.toml exists
+    if let Err(err) = apply_config_file(&mut options, &matches, &config) {
+        eprintln!("error: {}", err);
+        std::process::exit(1);
+    }
 
     adjust_defaults(&mut options);
 
     return options;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_options(args: &[&str]) -> ChDigOptions {
+        let mut argv = vec!["chdig"];
+        argv.extend_from_slice(args);
+        return ChDigOptions::parse_from(argv);
+    }
+
+    fn parse_options_with_matches(args: &[&str]) -> (ChDigOptions, clap::ArgMatches) {
+        let mut argv = vec!["chdig"];
+        argv.extend_from_slice(args);
+        let matches = ChDigOptions::command().get_matches_from(argv);
+        let options = ChDigOptions::from_arg_matches(&matches).unwrap();
+        return (options, matches);
+    }
+
+    #[test]
+    fn split_hosts_single_host_is_unchanged() {
+        let (primary, extra_hosts) = split_hosts("tcp://user@127.1:9000/?foo=bar");
+        assert_eq!(primary, "tcp://user@127.1:9000/?foo=bar");
+        assert!(extra_hosts.is_empty());
+    }
+
+    #[test]
+    fn split_hosts_splits_comma_separated_authority() {
+        let (primary, extra_hosts) = split_hosts("tcp://user@host1:9000,host2:9001,host3/db");
+        assert_eq!(primary, "tcp://user@host1:9000/db");
+        assert_eq!(extra_hosts, vec!["host2:9001".to_string(), "host3".to_string()]);
+    }
+
+    #[test]
+    fn split_hosts_without_scheme_or_userinfo() {
+        let (primary, extra_hosts) = split_hosts("host1,host2:9001");
+        assert_eq!(primary, "host1");
+        assert_eq!(extra_hosts, vec!["host2:9001".to_string()]);
+    }
+
+    #[test]
+    fn parse_key_value_splits_on_first_equals() {
+        assert_eq!(
+            parse_key_value("max_threads=4").unwrap(),
+            ("max_threads".to_string(), "4".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_key_value_rejects_missing_equals() {
+        assert!(parse_key_value("max_threads").is_err());
+    }
+
+    #[test]
+    fn secure_scheme_defaults_port_on_every_failover_host() {
+        let mut options = parse_options(&["-u", "tcps://host1,host2:9001", "--host", "host3"]);
+        adjust_defaults(&mut options);
+        assert_eq!(
+            options.clickhouse.hosts,
+            vec![
+                "host1:9440".to_string(),
+                "host2:9001".to_string(),
+                "host3:9440".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn setting_overrides_builtin_default_without_duplicating_key() {
+        let mut options = parse_options(&["--setting", "pool_min=5"]);
+        adjust_defaults(&mut options);
+        assert_eq!(options.clickhouse.url.matches("pool_min").count(), 1);
+        let pairs: HashMap<_, _> = url::Url::parse(&options.clickhouse.url)
+            .unwrap()
+            .query_pairs()
+            .into_owned()
+            .collect();
+        assert_eq!(pairs.get("pool_min"), Some(&"5".to_string()));
+    }
+
+    #[test]
+    fn config_file_fills_unset_fields() {
+        let (mut options, matches) = parse_options_with_matches(&[]);
+        let config: ConfigFile = toml::from_str(
+            r#"
+            [clickhouse]
+            url = "tcp://from-file:9000"
+            pool_min = 3
+            "#,
+        )
+        .unwrap();
+        apply_config_file(&mut options, &matches, &config).unwrap();
+        assert_eq!(options.clickhouse.url, "tcp://from-file:9000");
+        assert_eq!(options.clickhouse.pool_min, 3);
+    }
+
+    #[test]
+    fn cli_flag_wins_over_config_file() {
+        let (mut options, matches) = parse_options_with_matches(&["-u", "tcp://from-cli:9000"]);
+        let config: ConfigFile = toml::from_str(
+            r#"
+            [clickhouse]
+            url = "tcp://from-file:9000"
+            "#,
+        )
+        .unwrap();
+        apply_config_file(&mut options, &matches, &config).unwrap();
+        assert_eq!(options.clickhouse.url, "tcp://from-cli:9000");
+    }
+
+    #[test]
+    fn connection_profile_overrides_top_level_config_and_sets_group_by() {
+        let (mut options, matches) = parse_options_with_matches(&["--connection", "prod"]);
+        let config: ConfigFile = toml::from_str(
+            r#"
+            [clickhouse]
+            url = "tcp://default-host:9000"
+
+            [connections.prod]
+            url = "tcp://prod-host:9000"
+            cluster = "prod-cluster"
+            "#,
+        )
+        .unwrap();
+        apply_config_file(&mut options, &matches, &config).unwrap();
+        assert_eq!(options.clickhouse.url, "tcp://prod-host:9000");
+        assert_eq!(options.clickhouse.cluster, Some("prod-cluster".to_string()));
+        assert!(options.view.group_by);
+    }
+
+    #[test]
+    fn unknown_connection_profile_errors_instead_of_falling_back_silently() {
+        let (mut options, matches) = parse_options_with_matches(&["--connection", "missing"]);
+        let config = ConfigFile::default();
+        let err = apply_config_file(&mut options, &matches, &config).unwrap_err();
+        assert!(err.contains("missing"));
+    }
+
+    #[test]
+    fn param_is_prefixed_and_merged_into_connection_url() {
+        let mut options = parse_options(&["--param", "threshold=100"]);
+        adjust_defaults(&mut options);
+        assert_eq!(options.clickhouse.url.matches("param_threshold").count(), 1);
+        let pairs: HashMap<_, _> = url::Url::parse(&options.clickhouse.url)
+            .unwrap()
+            .query_pairs()
+            .into_owned()
+            .collect();
+        assert_eq!(pairs.get("param_threshold"), Some(&"100".to_string()));
+    }
+
+    #[test]
+    fn setting_and_param_are_merged_together_without_clobbering_each_other() {
+        let mut options = parse_options(&[
+            "--setting",
+            "pool_min=5",
+            "--param",
+            "threshold=100",
+        ]);
+        adjust_defaults(&mut options);
+        let pairs: HashMap<_, _> = url::Url::parse(&options.clickhouse.url)
+            .unwrap()
+            .query_pairs()
+            .into_owned()
+            .collect();
+        assert_eq!(pairs.get("pool_min"), Some(&"5".to_string()));
+        assert_eq!(pairs.get("param_threshold"), Some(&"100".to_string()));
+    }
+}